@@ -0,0 +1,69 @@
+// User-configurable server and yt-dlp settings, loaded from a TOML file
+// in the app data directory and merged over `start_server`'s defaults.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const CONFIG_FILE_NAME: &str = "voxtube.toml";
+
+/// Settings a user can tune via the settings screen: where downloads are
+/// cached, which `yt` binary to invoke, extra arguments to pass through to
+/// it (format selection, rate limits, output templates, proxy, ...), and
+/// where the server should run from. `None`/empty fields fall back to
+/// `start_server`'s built-in defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoxtubeConfig {
+    pub cache_dir: Option<PathBuf>,
+    pub yt_cli_path: Option<PathBuf>,
+    #[serde(default)]
+    pub extra_yt_args: Vec<String>,
+    pub working_directory: Option<PathBuf>,
+}
+
+impl VoxtubeConfig {
+    fn path(app_data_dir: &Path) -> PathBuf {
+        app_data_dir.join(CONFIG_FILE_NAME)
+    }
+
+    /// Load `voxtube.toml` from `app_data_dir`, writing out the defaults
+    /// on first launch so the file exists for a settings screen to edit.
+    ///
+    /// Returns a precise "file: line" error if the file exists but fails
+    /// to parse, so it can be surfaced on the error page instead of
+    /// silently falling back to defaults.
+    pub fn load_or_create(app_data_dir: &Path) -> Result<Self, String> {
+        let path = Self::path(app_data_dir);
+
+        if !path.exists() {
+            let default = Self::default();
+            default.save(app_data_dir)?;
+            return Ok(default);
+        }
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+
+        toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse config file {}: {}", path.display(), e))
+    }
+
+    /// Write this configuration back to `voxtube.toml` in `app_data_dir`.
+    pub fn save(&self, app_data_dir: &Path) -> Result<(), String> {
+        let path = Self::path(app_data_dir);
+        fs::create_dir_all(app_data_dir).map_err(|e| {
+            format!(
+                "Failed to create app data directory {}: {}",
+                app_data_dir.display(),
+                e
+            )
+        })?;
+
+        let contents =
+            toml::to_string_pretty(self).map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+        fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write config file {}: {}", path.display(), e))
+    }
+}
@@ -1,14 +1,167 @@
 // Server lifecycle management: port checking, health polling, logging, process spawn/shutdown.
 
+mod config;
+
+pub use config::VoxtubeConfig;
+
+use std::collections::VecDeque;
 use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
 use std::net::TcpStream;
 use std::path::Path;
 use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+
+/// First port `start_server` tries to bind the bundled server to.
+pub const SERVER_PORT_RANGE_START: u16 = 3847;
+
+/// Exclusive end of the candidate port range. A small range lets a second
+/// instance (or a leftover process holding the default port) coexist
+/// instead of hard-failing.
+pub const SERVER_PORT_RANGE_END: u16 = 3867;
+
+/// How often the health supervisor polls `/api/health` once the server is up.
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long the server may go without a successful health check before it
+/// is considered unhealthy and a restart is triggered.
+const UNHEALTHY_TIMEOUT: Duration = Duration::from_secs(35);
+
+/// Base and ceiling for the exponential backoff applied between restart
+/// attempts, to avoid hammering a server that keeps failing to come up.
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Give up restarting after this many consecutive failed attempts.
+const MAX_CONSECUTIVE_RESTART_FAILURES: u32 = 5;
+
+/// Holds the Bun server child process, protected by a mutex so event
+/// handlers and the health supervisor on different threads can safely
+/// access it.
+///
+/// `child` is briefly `None` while a restart is in flight; that is not
+/// the same as the app shutting down, so `shutting_down` is the
+/// authoritative signal for "stop supervising for good". `port` is kept
+/// here rather than as a value the supervisor captures once at spawn
+/// time, so that whoever last (re)started the server — the supervisor or
+/// the `restart_server` command — is always what the supervisor polls.
+///
+/// `restarting` is set for the duration of an actual take-down-and-respawn
+/// sequence and cleared again once it finishes (however it finishes). It's
+/// what lets the supervisor tell "someone is actively replacing `child`
+/// right now, re-check next tick" apart from "`child` is `None` because the
+/// last replacement attempt already failed and gave up" — the latter must
+/// not be mistaken for the former, or a failed restart silently wedges the
+/// supervisor forever instead of being retried.
+pub struct ServerState {
+    pub child: Option<Child>,
+    pub port: u16,
+    pub shutting_down: bool,
+    pub restarting: bool,
+}
+
+/// Maximum number of recent stdout/stderr lines retained for the
+/// diagnostics panel.
+const LOG_BUFFER_CAPACITY: usize = 500;
+
+/// Bounded in-memory tail of recent server stdout/stderr lines, shared
+/// with the frontend via the `get_server_logs` command.
+pub type LogBuffer = Mutex<VecDeque<String>>;
+
+/// Create an empty log buffer to be registered as Tauri managed state.
+pub fn new_log_buffer() -> Arc<LogBuffer> {
+    Arc::new(Mutex::new(VecDeque::with_capacity(LOG_BUFFER_CAPACITY)))
+}
+
+fn push_log_line(buffer: &LogBuffer, line: String) {
+    let mut buf = buffer.lock().unwrap();
+    if buf.len() == LOG_BUFFER_CAPACITY {
+        buf.pop_front();
+    }
+    buf.push_back(line);
+}
+
+/// Return the current contents of the server log ring buffer, oldest
+/// line first, for display in a diagnostics panel.
+#[tauri::command]
+pub fn get_server_logs(log_buffer: tauri::State<'_, Arc<LogBuffer>>) -> Vec<String> {
+    log_buffer.lock().unwrap().iter().cloned().collect()
+}
+
+/// Read the current `voxtube.toml`, for a settings screen to populate.
+#[tauri::command]
+pub fn get_config(app: tauri::AppHandle) -> Result<VoxtubeConfig, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    VoxtubeConfig::load_or_create(&app_data_dir)
+}
+
+/// Persist `config` to `voxtube.toml`. Callers should follow this with
+/// `restart_server` to apply it.
+#[tauri::command]
+pub fn set_config(app: tauri::AppHandle, config: VoxtubeConfig) -> Result<(), String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    config.save(&app_data_dir)
+}
+
+/// Tear down the currently running server and spawn a new one, picking up
+/// the latest `voxtube.toml`. Used by the settings screen after editing
+/// the config via `set_config`.
+///
+/// Errors out rather than proceeding if the health supervisor is already
+/// mid-restart — both paths take down and respawn the child independently,
+/// and letting them race would leave whichever `Child` finishes last
+/// clobbering `ServerState`, orphaning the other.
+#[tauri::command]
+pub fn restart_server(app: tauri::AppHandle) -> Result<u16, String> {
+    let state = app.state::<Mutex<ServerState>>();
+    {
+        let mut guard = state.lock().unwrap();
+        if guard.restarting {
+            return Err("A server restart is already in progress".to_string());
+        }
+        guard.restarting = true;
+        if let Some(mut child) = guard.child.take() {
+            shutdown_server(&mut child);
+        }
+    }
 
-const SERVER_PORT: u16 = 3847;
+    let result = start_server(&app);
+    let mut guard = state.lock().unwrap();
+    guard.restarting = false;
+    let (new_child, port) = result?;
+    guard.child = Some(new_child);
+    guard.port = port;
+    Ok(port)
+}
+
+/// Spawn a thread that copies lines from `reader` into both the log file
+/// and the shared ring buffer. The thread exits cleanly once the pipe
+/// closes, which happens when the child process exits or is killed.
+fn spawn_log_reader<R: std::io::Read + Send + 'static>(
+    reader: R,
+    mut log_file: File,
+    log_buffer: Arc<LogBuffer>,
+) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let _ = writeln!(log_file, "{}", line);
+            push_log_line(&log_buffer, line);
+        }
+    });
+}
 
 /// Check whether a TCP port is free to bind on localhost.
 ///
@@ -65,6 +218,62 @@ fn wait_for_server(port: u16, timeout: Duration) -> Result<(), String> {
     }
 }
 
+/// Capability strings the shell requires the bundled server to advertise.
+/// A stale cached binary or partial upgrade can leave the shell and server
+/// out of sync even though the server is otherwise healthy; this list is
+/// the contract that catches that mismatch at startup instead of at
+/// whatever feature first breaks.
+const REQUIRED_CAPABILITIES: &[&str] = &["download-progress"];
+
+/// Protocol version the shell was built against.
+const REQUIRED_PROTOCOL_VERSION: u32 = 1;
+
+/// Response body of `GET /api/capabilities`.
+#[derive(serde::Deserialize)]
+struct CapabilitiesResponse {
+    version: u32,
+    features: Vec<String>,
+}
+
+/// Query `/api/capabilities` and verify the server speaks a compatible
+/// protocol version and advertises every capability in
+/// `REQUIRED_CAPABILITIES`.
+///
+/// Returns a descriptive error identifying the specific mismatch (bad
+/// version or missing capability) that should flow into the error page.
+fn negotiate_capabilities(port: u16) -> Result<(), String> {
+    let url = format!("http://localhost:{}/api/capabilities", port);
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let response: CapabilitiesResponse = client
+        .get(&url)
+        .send()
+        .map_err(|e| format!("Failed to reach /api/capabilities: {}", e))?
+        .json()
+        .map_err(|e| format!("Malformed /api/capabilities response: {}", e))?;
+
+    if response.version != REQUIRED_PROTOCOL_VERSION {
+        return Err(format!(
+            "Server speaks protocol version {} but this build of VoxTube requires version {}; please reinstall VoxTube",
+            response.version, REQUIRED_PROTOCOL_VERSION
+        ));
+    }
+
+    for capability in REQUIRED_CAPABILITIES {
+        if !response.features.iter().any(|feature| feature == capability) {
+            return Err(format!(
+                "Server is missing required capability '{}'; please reinstall VoxTube",
+                capability
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 /// Create the logs directory and open a log file for server stdout/stderr.
 ///
 /// Returns a pair of file handles (stdout, stderr) that both point to the
@@ -91,9 +300,13 @@ fn setup_logging(app_data_dir: &Path) -> Result<(File, File), String> {
 
 /// Spawn the bundled Bun server binary and wait for it to become healthy.
 ///
-/// The child process is returned so the caller can hold onto it and
-/// shut it down later via `shutdown_server`.
-pub fn start_server(app: &tauri::AppHandle) -> Result<Child, String> {
+/// Scans `SERVER_PORT_RANGE_START..SERVER_PORT_RANGE_END` for the first
+/// free port and binds the server there, so a second instance (or a
+/// leftover process still holding the default port) doesn't prevent
+/// startup. The child process and the port it was bound to are returned
+/// so the caller can hold onto the child, shut it down later via
+/// `shutdown_server`, and build URLs against the actual port.
+pub fn start_server(app: &tauri::AppHandle) -> Result<(Child, u16), String> {
     // Determine the architecture-specific binary name.
     let arch_suffix = match std::env::consts::ARCH {
         "aarch64" => "aarch64",
@@ -109,44 +322,77 @@ pub fn start_server(app: &tauri::AppHandle) -> Result<Child, String> {
         .map_err(|e| format!("Failed to resolve resource directory: {}", e))?;
 
     let server_path = resource_dir.join("binaries").join(&binary_name);
-    let yt_cli_path = resource_dir.join("binaries").join("yt");
 
     let app_data_dir = app
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
 
+    // Load user-configurable overrides, creating the config file with
+    // defaults on first launch.
+    let config = VoxtubeConfig::load_or_create(&app_data_dir)?;
+    let cache_dir = config.cache_dir.clone().unwrap_or_else(|| app_data_dir.join("cache"));
+    let yt_cli_path = config
+        .yt_cli_path
+        .clone()
+        .unwrap_or_else(|| resource_dir.join("binaries").join("yt"));
+    let extra_yt_args = serde_json::to_string(&config.extra_yt_args)
+        .map_err(|e| format!("Failed to encode extra yt-dlp arguments: {}", e))?;
+
     // Set up log file handles.
     let (stdout_file, stderr_file) = setup_logging(&app_data_dir)?;
 
-    // Ensure the port is free before attempting to spawn.
-    if !check_port_available(SERVER_PORT) {
-        return Err(format!(
-            "Port {} is already in use. Is another instance of VoxTube running?",
-            SERVER_PORT
-        ));
-    }
+    // Pick the first free port in the candidate range.
+    let port = (SERVER_PORT_RANGE_START..SERVER_PORT_RANGE_END)
+        .find(|&candidate| check_port_available(candidate))
+        .ok_or_else(|| {
+            format!(
+                "No free port found in {}..{}. Is another instance of VoxTube running?",
+                SERVER_PORT_RANGE_START, SERVER_PORT_RANGE_END
+            )
+        })?;
 
-    // Spawn the server process.
-    let cache_dir = app_data_dir.join("cache");
-    let mut child = Command::new(&server_path)
+    // Spawn the server process, piping stdout/stderr so we can tee them
+    // into both the log file and the in-memory ring buffer.
+    let mut command = Command::new(&server_path);
+    command
         .env("CACHE_DIR", cache_dir.to_string_lossy().as_ref())
         .env("YT_CLI_PATH", yt_cli_path.to_string_lossy().as_ref())
-        .env("PORT", SERVER_PORT.to_string())
-        .stdout(Stdio::from(stdout_file))
-        .stderr(Stdio::from(stderr_file))
+        .env("YT_EXTRA_ARGS", &extra_yt_args)
+        .env("PORT", port.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(working_directory) = &config.working_directory {
+        command.current_dir(working_directory);
+    }
+    let mut child = command
         .spawn()
         .map_err(|e| format!("Failed to spawn server binary {}: {}", server_path.display(), e))?;
 
+    let log_buffer = app.state::<Arc<LogBuffer>>().inner().clone();
+    let stdout = child.stdout.take().expect("child stdout was piped");
+    let stderr = child.stderr.take().expect("child stderr was piped");
+    spawn_log_reader(stdout, stdout_file, log_buffer.clone());
+    spawn_log_reader(stderr, stderr_file, log_buffer);
+
     // Wait for the server to respond to health checks.
-    if let Err(msg) = wait_for_server(SERVER_PORT, Duration::from_secs(4)) {
+    if let Err(msg) = wait_for_server(port, Duration::from_secs(4)) {
         eprintln!("[voxtube] Health check failed, killing server process: {}", msg);
         let _ = child.kill();
         let _ = child.wait();
         return Err(msg);
     }
 
-    Ok(child)
+    // A healthy server isn't necessarily one that speaks the protocol this
+    // shell expects; verify that before handing the child back to the caller.
+    if let Err(msg) = negotiate_capabilities(port) {
+        eprintln!("[voxtube] Capability negotiation failed, killing server process: {}", msg);
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(msg);
+    }
+
+    Ok((child, port))
 }
 
 /// Gracefully shut down the server process.
@@ -195,3 +441,175 @@ fn graceful_shutdown_impl(child: &mut Child) {
     let _ = child.kill();
     let _ = child.wait();
 }
+
+/// A health-transition event emitted to the frontend so the webview can
+/// show a reconnect banner instead of a blank page while the server is
+/// down or being restarted. `port` is the port the server is (or, for
+/// `restarting`/`unhealthy`, was) reachable on; after a `healthy` event
+/// following a restart it may differ from the port the webview last
+/// navigated to.
+#[derive(Clone, serde::Serialize)]
+struct HealthEvent {
+    state: &'static str,
+    port: u16,
+}
+
+fn emit_health_event(app: &tauri::AppHandle, state: &'static str, port: u16) {
+    let _ = app.emit("server-health", HealthEvent { state, port });
+}
+
+/// Spawn a background thread that supervises the running server.
+///
+/// Every `HEALTH_POLL_INTERVAL` it checks `/api/health` on the port
+/// currently recorded in the managed `ServerState` (not a value captured
+/// once at spawn time, so it stays correct across restarts triggered by
+/// either this supervisor or the `restart_server` command) and the
+/// child's exit status. If the child has exited, or no successful health
+/// check has been seen for `UNHEALTHY_TIMEOUT`, the old child is torn down
+/// via `shutdown_server`, `start_server` is re-run, and the new `Child`
+/// and port are stored back into `ServerState`. Restart attempts back off
+/// exponentially and stop entirely after `MAX_CONSECUTIVE_RESTART_FAILURES`
+/// in a row, at which point the last restart error is emitted to the
+/// frontend as a `server-error` event. `main` does not listen for that
+/// event, so it is not shown on the error page.
+///
+/// `ServerState.child` is briefly `None` mid-restart. While
+/// `ServerState.restarting` is set, that tick is simply skipped rather than
+/// treated as a failed attempt; once it clears, a lingering `None` means
+/// the last restart attempt already gave up, so it's retried like any
+/// other unhealthy server instead of wedging the loop forever.
+/// `ServerState.shutting_down` is the only signal that ends the loop for
+/// good. `ServerState.restarting` also doubles as a mutual-exclusion guard
+/// against the `restart_server` command, so the two never race to take
+/// down and respawn the child at the same time.
+///
+/// Must be called only after `start_server` has succeeded and its `Child`
+/// and port have been stored in `app`'s managed `Mutex<ServerState>`.
+pub fn spawn_health_supervisor(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let client = match reqwest::blocking::Client::builder()
+            .timeout(Duration::from_millis(500))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("[voxtube] Supervisor failed to build HTTP client: {}", e);
+                return;
+            }
+        };
+
+        let mut last_healthy = Instant::now();
+        let mut consecutive_restart_failures: u32 = 0;
+        let mut last_restart_error = String::new();
+        let mut reported_healthy = true;
+
+        loop {
+            std::thread::sleep(HEALTH_POLL_INTERVAL);
+
+            let state = app.state::<Mutex<ServerState>>();
+
+            let (process_exited, port) = {
+                let mut guard = state.lock().unwrap();
+                if guard.shutting_down {
+                    return;
+                }
+                if guard.child.is_none() && guard.restarting {
+                    // Someone (this loop's own restart below, or a manual
+                    // `restart_server` call) is actively spawning a
+                    // replacement; re-check next tick instead of racing it.
+                    continue;
+                }
+                match guard.child.as_mut() {
+                    Some(child) => (matches!(child.try_wait(), Ok(Some(_))), guard.port),
+                    // The last restart attempt already failed and nothing is
+                    // retrying it; treat this like a dead process so the
+                    // retry/backoff/give-up logic below runs again instead of
+                    // silently wedging forever.
+                    None => (true, guard.port),
+                }
+            };
+
+            let url = format!("http://localhost:{}/api/health", port);
+            let responded = client
+                .get(&url)
+                .send()
+                .map(|resp| resp.status().is_success())
+                .unwrap_or(false);
+
+            if !process_exited && responded {
+                last_healthy = Instant::now();
+                if consecutive_restart_failures > 0 {
+                    consecutive_restart_failures = 0;
+                }
+                if !reported_healthy {
+                    emit_health_event(&app, "healthy", port);
+                    reported_healthy = true;
+                }
+                continue;
+            }
+
+            if !process_exited && last_healthy.elapsed() <= UNHEALTHY_TIMEOUT {
+                // A single missed poll isn't worth restarting over.
+                continue;
+            }
+
+            emit_health_event(&app, "unhealthy", port);
+            reported_healthy = false;
+
+            if consecutive_restart_failures >= MAX_CONSECUTIVE_RESTART_FAILURES {
+                let msg = format!(
+                    "Server became unresponsive and failed to restart after {} attempts: {}",
+                    MAX_CONSECUTIVE_RESTART_FAILURES, last_restart_error
+                );
+                eprintln!("[voxtube] {}", msg);
+                let _ = app.emit("server-error", msg);
+                return;
+            }
+
+            if consecutive_restart_failures > 0 {
+                let backoff = RESTART_BACKOFF_BASE
+                    .saturating_mul(1 << consecutive_restart_failures.min(8))
+                    .min(RESTART_BACKOFF_MAX);
+                std::thread::sleep(backoff);
+            }
+
+            emit_health_event(&app, "restarting", port);
+
+            {
+                let mut guard = state.lock().unwrap();
+                if guard.shutting_down {
+                    return;
+                }
+                if guard.restarting {
+                    // A `restart_server` call got here first; let it finish.
+                    continue;
+                }
+                guard.restarting = true;
+                if let Some(mut child) = guard.child.take() {
+                    shutdown_server(&mut child);
+                }
+            }
+
+            match start_server(&app) {
+                Ok((new_child, new_port)) => {
+                    let mut guard = state.lock().unwrap();
+                    guard.child = Some(new_child);
+                    guard.port = new_port;
+                    guard.restarting = false;
+                    drop(guard);
+                    last_healthy = Instant::now();
+                    consecutive_restart_failures = 0;
+                    emit_health_event(&app, "healthy", new_port);
+                    reported_healthy = true;
+                }
+                Err(e) => {
+                    eprintln!("[voxtube] Restart attempt failed: {}", e);
+                    last_restart_error = e;
+                    consecutive_restart_failures += 1;
+                    let mut guard = state.lock().unwrap();
+                    guard.restarting = false;
+                }
+            }
+        }
+    });
+}
@@ -2,17 +2,26 @@
 
 use std::sync::Mutex;
 use tauri::Manager;
-use voxtube_lib::{shutdown_server, start_server};
-
-/// Holds the Bun server child process, protected by a mutex so event
-/// handlers on different threads can safely access it.
-struct ServerState {
-    child: Option<std::process::Child>,
-}
+use voxtube_lib::{
+    get_config, get_server_logs, new_log_buffer, restart_server, set_config, shutdown_server,
+    spawn_health_supervisor, start_server, ServerState,
+};
 
 fn main() {
     tauri::Builder::default()
-        .manage(Mutex::new(ServerState { child: None }))
+        .manage(Mutex::new(ServerState {
+            child: None,
+            port: 0,
+            shutting_down: false,
+            restarting: false,
+        }))
+        .manage(new_log_buffer())
+        .invoke_handler(tauri::generate_handler![
+            get_server_logs,
+            get_config,
+            set_config,
+            restart_server
+        ])
         .setup(|app| {
             let app_handle = app.handle().clone();
 
@@ -26,6 +35,7 @@ fn main() {
                     if let tauri::WindowEvent::CloseRequested { .. } = event {
                         let state = close_handle.state::<Mutex<ServerState>>();
                         let mut guard = state.lock().unwrap();
+                        guard.shutting_down = true;
                         if let Some(ref mut child) = guard.child {
                             shutdown_server(child);
                         }
@@ -41,15 +51,21 @@ fn main() {
                     .expect("main window not found");
 
                 match start_server(&app_handle) {
-                    Ok(child) => {
-                        // Store the child process in managed state.
+                    Ok((child, port)) => {
+                        // Store the child process and its port in managed state.
                         let state = app_handle.state::<Mutex<ServerState>>();
                         let mut guard = state.lock().unwrap();
                         guard.child = Some(child);
+                        guard.port = port;
                         drop(guard);
 
+                        // Keep watching the server after startup and restart it
+                        // automatically if it stops responding.
+                        spawn_health_supervisor(app_handle.clone());
+
                         // Navigate the webview to the running server.
-                        let _ = window.navigate("http://localhost:3847".parse().unwrap());
+                        let url = format!("http://localhost:{}", port);
+                        let _ = window.navigate(url.parse().unwrap());
                     }
                     Err(error_msg) => {
                         // Inject the error message into the error page.
@@ -72,6 +88,7 @@ fn main() {
             if let tauri::RunEvent::Exit = event {
                 let state = app_handle.state::<Mutex<ServerState>>();
                 let mut guard = state.lock().unwrap();
+                guard.shutting_down = true;
                 if let Some(ref mut child) = guard.child {
                     shutdown_server(child);
                 }